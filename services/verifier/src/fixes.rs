@@ -0,0 +1,201 @@
+//! Machine-applicable fix suggestions, and applying them back to source --
+//! the same shape compiletest feeds through `rustfix::apply_suggestions`.
+
+use rustpython_parser::ast::{self, Ranged, Stmt};
+
+use crate::source_map::SourceMap;
+use crate::verifier::{Applicability, Suggestion, TextRange};
+
+/// A stub-docstring insertion for a `DOCSTRING_MISSING` issue.
+pub fn docstring_stub_suggestion(name: &str, insert_offset: usize, source_map: &SourceMap) -> Suggestion {
+    let (_, column) = source_map.location(insert_offset);
+    let indent = " ".repeat((column - 1) as usize);
+    let replacement = format!("\"\"\"TODO: document {}.\"\"\"\n{}", name, indent);
+    Suggestion {
+        range: Some(TextRange { start: insert_offset as u32, end: insert_offset as u32 }),
+        replacement,
+        applicability: Applicability::MachineApplicable as i32,
+    }
+}
+
+/// A bare `except:` found anywhere in the tree, with a suggestion to
+/// narrow it to `except Exception:`.
+pub struct BareExcept {
+    pub line: u32,
+    pub column: u32,
+    pub suggestion: Suggestion,
+}
+
+pub fn find_bare_excepts(body: &[Stmt], source_map: &SourceMap) -> Vec<BareExcept> {
+    let mut found = Vec::new();
+    for stmt in body {
+        visit_stmt(stmt, source_map, &mut found);
+    }
+    found
+}
+
+fn visit_stmt(stmt: &Stmt, source_map: &SourceMap, out: &mut Vec<BareExcept>) {
+    match stmt {
+        Stmt::Try(t) => {
+            for handler in &t.handlers {
+                let ast::ExceptHandler::ExceptHandler(h) = handler;
+                if h.type_.is_none() {
+                    if let Some(colon_offset) = find_except_colon(source_map, h.range().start().to_usize()) {
+                        let (line, column) = source_map.location(h.range().start().to_usize());
+                        out.push(BareExcept {
+                            line,
+                            column,
+                            suggestion: Suggestion {
+                                range: Some(TextRange { start: colon_offset as u32, end: colon_offset as u32 }),
+                                replacement: " Exception".to_string(),
+                                applicability: Applicability::MaybeIncorrect as i32,
+                            },
+                        });
+                    }
+                }
+                for s in &h.body {
+                    visit_stmt(s, source_map, out);
+                }
+            }
+            for s in t.body.iter().chain(&t.orelse).chain(&t.finalbody) {
+                visit_stmt(s, source_map, out);
+            }
+        }
+        Stmt::FunctionDef(f) => visit_all(&f.body, source_map, out),
+        Stmt::AsyncFunctionDef(f) => visit_all(&f.body, source_map, out),
+        Stmt::ClassDef(c) => visit_all(&c.body, source_map, out),
+        Stmt::If(i) => {
+            visit_all(&i.body, source_map, out);
+            visit_all(&i.orelse, source_map, out);
+        }
+        Stmt::For(f) => {
+            visit_all(&f.body, source_map, out);
+            visit_all(&f.orelse, source_map, out);
+        }
+        Stmt::While(w) => {
+            visit_all(&w.body, source_map, out);
+            visit_all(&w.orelse, source_map, out);
+        }
+        Stmt::With(w) => visit_all(&w.body, source_map, out),
+        Stmt::AsyncWith(w) => visit_all(&w.body, source_map, out),
+        _ => {}
+    }
+}
+
+fn visit_all(body: &[Stmt], source_map: &SourceMap, out: &mut Vec<BareExcept>) {
+    for stmt in body {
+        visit_stmt(stmt, source_map, out);
+    }
+}
+
+/// A bare handler's range starts at the `except` keyword; find the colon
+/// that ends its header (`except:`) so the suggestion can insert the
+/// exception type right before it.
+fn find_except_colon(source_map: &SourceMap, handler_start: usize) -> Option<usize> {
+    let source = source_map.source();
+    let rest = source.get(handler_start..)?;
+    let after_keyword = rest.strip_prefix("except")?;
+    let colon_in_rest = after_keyword.find(':')?;
+    Some(handler_start + "except".len() + colon_in_rest)
+}
+
+/// Apply every `MachineApplicable` suggestion to `source`: sort by start
+/// offset and skip any suggestion whose range overlaps one already applied,
+/// mirroring `rustfix::apply_suggestions`'s conflict handling.
+pub fn apply_suggestions(source: &str, suggestions: &[Suggestion]) -> String {
+    let mut applicable: Vec<&Suggestion> = suggestions
+        .iter()
+        .filter(|s| s.applicability == Applicability::MachineApplicable as i32 && s.range.is_some())
+        .collect();
+    applicable.sort_by_key(|s| s.range.as_ref().unwrap().start);
+
+    let mut out = String::new();
+    let mut cursor = 0usize;
+    for s in applicable {
+        let range = s.range.as_ref().unwrap();
+        let (start, end) = (range.start as usize, range.end as usize);
+        if start < cursor {
+            continue;
+        }
+        out.push_str(&source[cursor..start]);
+        out.push_str(&s.replacement);
+        cursor = end;
+    }
+    out.push_str(&source[cursor..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpython_parser::ast::Mod;
+    use rustpython_parser::{parse, Mode};
+
+    fn module_body(code: &str) -> Vec<Stmt> {
+        match parse(code, Mode::Module, "<test>").unwrap() {
+            Mod::Module(m) => m.body,
+            _ => panic!("expected a module"),
+        }
+    }
+
+    fn machine_applicable(start: u32, end: u32, replacement: &str) -> Suggestion {
+        Suggestion {
+            range: Some(TextRange { start, end }),
+            replacement: replacement.to_string(),
+            applicability: Applicability::MachineApplicable as i32,
+        }
+    }
+
+    #[test]
+    fn apply_suggestions_inserts_a_stub_docstring_with_the_right_indent() {
+        let source = "def foo():\n    pass\n";
+        let source_map = SourceMap::new(source);
+        // Offset 15 is right after "def foo():\n    ", i.e. where the body starts.
+        let insert_offset = 15;
+        let suggestion = docstring_stub_suggestion("foo", insert_offset, &source_map);
+        let fixed = apply_suggestions(source, std::slice::from_ref(&suggestion));
+        assert_eq!(fixed, "def foo():\n    \"\"\"TODO: document foo.\"\"\"\n    pass\n");
+    }
+
+    #[test]
+    fn apply_suggestions_applies_the_first_of_two_overlapping_ranges_and_skips_the_second() {
+        let source = "0123456789";
+        let first = machine_applicable(2, 5, "AAA");
+        let second = machine_applicable(4, 7, "BBB");
+        let fixed = apply_suggestions(source, &[first, second]);
+        // second overlaps [2, 5) at offset 4, so only the first is applied.
+        assert_eq!(fixed, "01AAA56789");
+    }
+
+    #[test]
+    fn apply_suggestions_applies_two_non_overlapping_ranges() {
+        let source = "0123456789";
+        let first = machine_applicable(2, 4, "AA");
+        let second = machine_applicable(6, 8, "BB");
+        let fixed = apply_suggestions(source, &[first, second]);
+        assert_eq!(fixed, "01AA45BB89");
+    }
+
+    #[test]
+    fn find_bare_excepts_flags_a_bare_handler_but_not_a_typed_one_after_it() {
+        let code = "try:\n    risky()\nexcept:\n    pass\ntry:\n    risky()\nexcept ValueError:\n    pass\n";
+        let body = module_body(code);
+        let source_map = SourceMap::new(code);
+        let found = find_bare_excepts(&body, &source_map);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].suggestion.replacement, " Exception");
+    }
+
+    #[test]
+    fn find_bare_excepts_places_the_suggestion_right_before_the_colon() {
+        let code = "try:\n    risky()\nexcept:\n    pass\n";
+        let body = module_body(code);
+        let source_map = SourceMap::new(code);
+        let found = find_bare_excepts(&body, &source_map);
+        let range = found[0].suggestion.range.as_ref().unwrap();
+        assert_eq!(range.start, range.end);
+        let colon_offset = range.start as usize;
+        assert_eq!(&code[colon_offset..colon_offset + 1], ":");
+        assert_eq!(&code[colon_offset - "except".len()..colon_offset], "except");
+    }
+}
@@ -1,3 +1,23 @@
+//! Satisfiability checking for symbolic constraints via Z3.
+
+use anyhow::Result;
+use z3::{Config, Context, SatResult, Solver};
+
+/// Result of checking a set of constraints, richer than a bare bool: on
+/// SAT we hand back a concrete counterexample, on UNSAT the contradicting
+/// subset Z3 reports as its unsat core. No core minimization is requested,
+/// so the core may include assertions the contradiction does not need.
+pub enum SmtVerdict {
+    Sat { model: String },
+    Unsat { unsat_core: Vec<String> },
+    Unknown,
+}
+
+/// Solver timeout used when a caller omits (or sends a literal `0` for)
+/// `solver_timeout_ms`, mirroring how `SandboxLimits::from_proto` falls back
+/// to a default instead of collapsing an unset field to a zero budget.
+const DEFAULT_SOLVER_TIMEOUT_MS: u64 = 5_000;
+
 pub struct SmtVerifier {}
 
 impl SmtVerifier {
@@ -5,15 +25,102 @@ impl SmtVerifier {
         Self {}
     }
 
-    /// Verify if a set of constraints is satisfiable.
-    /// (Z3 Solver temporarily replaced with heuristic check due to build environment limits)
-    pub fn verify_constraints(&self, constraints: Vec<String>) -> bool {
-        // Mock Logic: If constraint contains "fail", return false. Else true.
-        for c in constraints {
-            if c.contains("fail") || c.contains("false") {
-                return false;
+    /// Check whether `declarations` + `assertions` are jointly satisfiable.
+    /// Each assertion is wrapped in a `:named` annotation so an UNSAT result
+    /// can name the ones in the core, not just say "some of them do".
+    /// A solver timeout is reported as `Unknown` rather than an error.
+    /// `solver_timeout_ms == 0` (proto3 can't distinguish "omitted" from
+    /// "zero") falls back to `DEFAULT_SOLVER_TIMEOUT_MS` rather than handing
+    /// Z3 a zero-length budget.
+    pub fn verify_constraints(
+        &self,
+        declarations: &[String],
+        assertions: &[String],
+        solver_timeout_ms: u64,
+    ) -> Result<SmtVerdict> {
+        let solver_timeout_ms = if solver_timeout_ms == 0 { DEFAULT_SOLVER_TIMEOUT_MS } else { solver_timeout_ms };
+
+        let mut config = Config::new();
+        config.set_timeout_msec(solver_timeout_ms);
+        let ctx = Context::new(&config);
+        let solver = Solver::new(&ctx);
+
+        // Must be set before any `(assert ...)` or `get-unsat-core` comes
+        // back empty even on a genuine UNSAT result.
+        let mut script = String::from("(set-option :produce-unsat-cores true)\n");
+        for decl in declarations {
+            script.push_str(decl);
+            script.push('\n');
+        }
+        for (i, assertion) in assertions.iter().enumerate() {
+            script.push_str(&format!("(assert (! {assertion} :named a{i}))\n"));
+        }
+        solver.from_string(script);
+
+        let verdict = match solver.check() {
+            SatResult::Sat => {
+                let model = solver.get_model().map(|m| m.to_string()).unwrap_or_default();
+                SmtVerdict::Sat { model }
+            }
+            SatResult::Unsat => {
+                let unsat_core = solver.get_unsat_core().iter().map(|a| a.to_string()).collect();
+                SmtVerdict::Unsat { unsat_core }
             }
+            SatResult::Unknown => SmtVerdict::Unknown,
+        };
+        Ok(verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sat_constraints_produce_a_model() {
+        let verifier = SmtVerifier::new();
+        let declarations = vec!["(declare-const x Int)".to_string()];
+        let assertions = vec!["(> x 0)".to_string(), "(< x 10)".to_string()];
+        match verifier.verify_constraints(&declarations, &assertions, 5_000).unwrap() {
+            SmtVerdict::Sat { model } => assert!(!model.is_empty()),
+            _ => panic!("expected Sat, got a different verdict"),
+        }
+    }
+
+    #[test]
+    fn contradicting_constraints_report_an_unsat_core() {
+        let verifier = SmtVerifier::new();
+        let declarations = vec!["(declare-const x Int)".to_string()];
+        let assertions = vec!["(> x 0)".to_string(), "(< x 0)".to_string()];
+        match verifier.verify_constraints(&declarations, &assertions, 5_000).unwrap() {
+            SmtVerdict::Unsat { unsat_core } => assert!(!unsat_core.is_empty()),
+            _ => panic!("expected Unsat, got a different verdict"),
+        }
+    }
+
+    #[test]
+    fn a_quantified_formula_does_not_error_out_even_if_the_solver_gives_up() {
+        // We can't force Z3 to time out deterministically, but a quantified
+        // formula should at least exercise the `Unknown` arm without the
+        // call itself erroring.
+        let verifier = SmtVerifier::new();
+        let declarations = vec!["(declare-const xs (Array Int Int))".to_string()];
+        let assertions = vec!["(forall ((i Int)) (> (select xs i) i))".to_string()];
+        let result = verifier.verify_constraints(&declarations, &assertions, 50);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn an_omitted_timeout_falls_back_to_the_default_instead_of_zero() {
+        // solver_timeout_ms == 0 must not hand Z3 a zero-length budget --
+        // proto3 can't distinguish "caller didn't set this" from "caller
+        // set it to 0", so this has to behave like the field was omitted.
+        let verifier = SmtVerifier::new();
+        let declarations = vec!["(declare-const x Int)".to_string()];
+        let assertions = vec!["(> x 0)".to_string(), "(< x 10)".to_string()];
+        match verifier.verify_constraints(&declarations, &assertions, 0).unwrap() {
+            SmtVerdict::Sat { model } => assert!(!model.is_empty()),
+            _ => panic!("expected a 0ms timeout to fall back to a real budget and solve this trivially"),
         }
-        true
     }
 }
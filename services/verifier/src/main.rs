@@ -1,19 +1,50 @@
+use std::pin::Pin;
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
 use tonic::{transport::Server, Request, Response, Status};
 use verifier::verifier_service_server::{VerifierService, VerifierServiceServer};
-use verifier::{VerifyRequest, VerifyResponse, Issue};
+use verifier::{
+    SmtVerdict as ProtoSmtVerdict, SmtVerifyRequest, SmtVerifyResponse, TestResult, TestRunRequest,
+    VerifyRequest, VerifyResponse, Issue, WasmVerifyRequest, WasmVerifyResponse,
+};
 use rustpython_parser::{parse, Mode};
 use rustpython_parser::ast;
-use rustpython_parser::ast::{Mod, Stmt};
+use rustpython_parser::ast::{Mod, Ranged, Stmt};
+
+mod fixes;
+mod formal;
+mod output_match;
+mod py_sandbox;
+mod sandbox;
+mod source_map;
+mod test_runner;
+use formal::{SmtVerdict, SmtVerifier};
+use py_sandbox::{LimitTripped, SandboxLimits};
+use sandbox::WasmSandbox;
+use source_map::SourceMap;
+
+/// A definition missing a docstring, located for reporting as an `Issue`.
+struct MissingDoc {
+    name: String,
+    line: u32,
+    column: u32,
+    /// Byte offset of the first statement in the definition's body, i.e.
+    /// where a stub docstring should be inserted.
+    insert_offset: usize,
+}
 
-struct DocstringChecker {
+struct DocstringChecker<'a> {
+    source_map: &'a SourceMap,
     total_definitions: usize,
     documented: usize,
-    missing_docs: Vec<String>,
+    missing_docs: Vec<MissingDoc>,
 }
 
-impl DocstringChecker {
-    fn new() -> Self {
-        Self { total_definitions: 0, documented: 0, missing_docs: Vec::new() }
+impl<'a> DocstringChecker<'a> {
+    fn new(source_map: &'a SourceMap) -> Self {
+        Self { source_map, total_definitions: 0, documented: 0, missing_docs: Vec::new() }
     }
 
     fn check(&mut self, ast: &Vec<Stmt>) {
@@ -24,14 +55,14 @@ impl DocstringChecker {
 
     fn visit_stmt(&mut self, stmt: &Stmt) {
         match stmt {
-            Stmt::FunctionDef(f) => self.check_def(&f.body, &f.name.as_str()),
-            Stmt::AsyncFunctionDef(f) => self.check_def(&f.body, &f.name.as_str()),
-            Stmt::ClassDef(c) => self.check_def(&c.body, &c.name.as_str()),
+            Stmt::FunctionDef(f) => self.check_def(&f.body, f.name.as_str(), f.range()),
+            Stmt::AsyncFunctionDef(f) => self.check_def(&f.body, f.name.as_str(), f.range()),
+            Stmt::ClassDef(c) => self.check_def(&c.body, c.name.as_str(), c.range()),
             _ => {}
         }
     }
 
-    fn check_def(&mut self, body: &Vec<Stmt>, name: &str) {
+    fn check_def(&mut self, body: &Vec<Stmt>, name: &str, range: ast::TextRange) {
         self.total_definitions += 1;
         if let Some(doc_stmt) = body.first() {
             if let Stmt::Expr(expr_stmt) = doc_stmt {
@@ -43,7 +74,9 @@ impl DocstringChecker {
                  }
             }
         }
-        self.missing_docs.push(name.to_string());
+        let (line, column) = self.source_map.location(range.start().to_usize());
+        let insert_offset = body.first().map(|s| s.range().start().to_usize()).unwrap_or(range.end().to_usize());
+        self.missing_docs.push(MissingDoc { name: name.to_string(), line, column, insert_offset });
     }
 }
 
@@ -52,6 +85,27 @@ pub mod verifier {
     tonic::include_proto!("verifier");
 }
 
+/// Pull the line number out of a Python traceback frame like
+/// `File "<embedded>", line 12, in <module>`.
+fn traceback_line(output: &str) -> Option<u32> {
+    output.lines().find_map(|l| {
+        let l = l.trim();
+        if !l.starts_with("File \"<embedded>\"") {
+            return None;
+        }
+        let after = l.split("line ").nth(1)?;
+        after.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+    })
+}
+
+fn limit_tripped_message(tripped: LimitTripped, result: &py_sandbox::ExecutionResult) -> String {
+    match tripped {
+        LimitTripped::Cpu => format!("CPU time limit exceeded (used ~{} ms)", result.cpu_time_ms),
+        LimitTripped::Memory => format!("Memory limit exceeded (peak ~{} KB)", result.peak_rss_kb),
+        LimitTripped::Timeout => "Execution exceeded the wall-clock timeout".to_string(),
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct MyVerifier {}
 
@@ -65,7 +119,8 @@ impl VerifierService for MyVerifier {
         let code = req.code;
         let mut issues = Vec::new();
         let mut valid = true;
-        
+        let source_map = SourceMap::new(&code);
+
         // 1. Syntax Check (Python)
         // Only run if language is python or unspecified
         if req.language.to_lowercase() == "python" || req.language.is_empty() {
@@ -75,12 +130,14 @@ impl VerifierService for MyVerifier {
                 },
                 Err(e) => {
                     valid = false;
+                    let (line, column) = source_map.location(usize::from(e.offset));
                     issues.push(Issue {
                         code: "SYNTAX_ERROR".to_string(),
                         message: format!("{}", e.error),
                         severity: "error".to_string(),
-                        line: 0,
-                        column: u32::from(e.offset) as i32,
+                        line: line as i32,
+                        column: column as i32,
+                        suggestions: vec![],
                     });
                 }
             }
@@ -92,9 +149,9 @@ impl VerifierService for MyVerifier {
             match parse(&code, Mode::Module, "<embedded>") {
                 Ok(module_ast) => { // Rename ast to module_ast logic
                     if let Mod::Module(m) = module_ast {
-                        let mut checker = DocstringChecker::new();
+                        let mut checker = DocstringChecker::new(&source_map);
                         checker.check(&m.body);
-                        
+
                         if checker.total_definitions > 0 {
                             let ratio = checker.documented as f32 / checker.total_definitions as f32;
                             if ratio < 0.5 {
@@ -105,15 +162,17 @@ impl VerifierService for MyVerifier {
                                     severity: "warning".to_string(),
                                     line: 0,
                                     column: 0,
+                                    suggestions: vec![],
                                 });
                             }
-                             for missing in checker.missing_docs.iter().take(5) { 
+                             for missing in checker.missing_docs.iter().take(5) {
                                  issues.push(Issue {
                                     code: "DOCSTRING_MISSING".to_string(),
-                                    message: format!("'{}' lacks a docstring", missing),
+                                    message: format!("'{}' lacks a docstring", missing.name),
                                     severity: "warning".to_string(),
-                                    line: 0,
-                                    column: 0,
+                                    line: missing.line as i32,
+                                    column: missing.column as i32,
+                                    suggestions: vec![fixes::docstring_stub_suggestion(&missing.name, missing.insert_offset, &source_map)],
                                 });
                             }
                         }
@@ -125,83 +184,207 @@ impl VerifierService for MyVerifier {
             }
         }
 
-        // 3. Execution Check
-        if req.checks.contains(&"execution".to_string()) && (req.language.to_lowercase() == "python" || req.language.is_empty()) {
-             // Create a temp file with the sandbox wrapper
-             use std::io::Write;
-             use std::process::Command;
-             
-             let wrapper_code = format!(r#"
-import sys
-from io import StringIO
-old_stdout = sys.stdout
-sys.stdout = StringIO()
-try:
-    exec_globals = {{"__builtins__": __builtins__}}
-    exec("""{}""", exec_globals)
-    print("__EXECUTION_SUCCESS__")
-except Exception as e:
-    print(f"__EXECUTION_ERROR__: {{type(e).__name__}}: {{str(e)}}")
-output = sys.stdout.getvalue()
-sys.stdout = old_stdout
-print(output)
-"#, code.replace("\"", "\\\"").replace("\\", "\\\\")); // Basic escaping, might be fragile
-
-             let mut temp_dir = std::env::temp_dir();
-             temp_dir.push(format!("axiom_exec_{}.py", uuid::Uuid::new_v4()));
-             
-             if let Ok(mut file) = std::fs::File::create(&temp_dir) {
-                 if let Ok(_) = file.write_all(wrapper_code.as_bytes()) {
-                     // Run python (using py launcher for Windows compatibility)
-                     // Try "py" first, then "python"
-                     let output = Command::new("py").arg(&temp_dir).output()
-                        .or_else(|_| Command::new("python").arg(&temp_dir).output());
-
-                     if let Ok(output) = output {
-                         let stdout = String::from_utf8_lossy(&output.stdout);
-                         let stderr = String::from_utf8_lossy(&output.stderr);
-                         
-                         if stdout.contains("__EXECUTION_SUCCESS__") {
-                             // Passed
-                         } else if stdout.contains("__EXECUTION_ERROR__") {
-                             valid = false;
-                             let error_msg = stdout.lines().find(|l| l.contains("__EXECUTION_ERROR__")).unwrap_or("Unknown execution error");
-                             issues.push(Issue {
-                                code: "EXECUTION_ERROR".to_string(),
-                                message: error_msg.to_string(),
-                                severity: "error".to_string(),
-                                line: 0,
-                                column: 0,
-                            });
-                         } else {
-                              valid = false;
-                              issues.push(Issue {
-                                code: "EXECUTION_FAIL".to_string(),
-                                message: format!("Execution failed or no output. Stderr: {}", stderr),
-                                severity: "error".to_string(),
-                                line: 0,
-                                column: 0,
-                            });
-                         }
-                     } else {
-                          valid = false;
-                          issues.push(Issue {
-                                code: "EXECUTION_SPAWN_FAIL".to_string(),
-                                message: "Failed to spawn python interpreter".to_string(),
-                                severity: "error".to_string(),
-                                line: 0,
-                                column: 0,
-                            });
-                     }
-                 }
-                 let _ = std::fs::remove_file(&temp_dir);
-             }
+        // 3. Execution Check (resource-limited sandbox; see py_sandbox.rs)
+        let wants_execution = req.checks.contains(&"execution".to_string());
+        let wants_output_match = req.checks.contains(&"output_match".to_string());
+        let mut execution_result: Option<py_sandbox::ExecutionResult> = None;
+
+        if (wants_execution || wants_output_match) && (req.language.to_lowercase() == "python" || req.language.is_empty()) {
+            let limits = SandboxLimits::from_proto(req.sandbox_limits.as_ref());
+
+            match py_sandbox::run_python(&code, limits) {
+                Ok(result) => {
+                    if let Some(tripped) = result.tripped {
+                        valid = false;
+                        issues.push(Issue {
+                            code: tripped.issue_code().to_string(),
+                            message: limit_tripped_message(tripped, &result),
+                            severity: "error".to_string(),
+                            line: 0,
+                            column: 0,
+                            suggestions: vec![],
+                        });
+                    } else if result.exec_success {
+                        // Passed
+                    } else if let Some(error_msg) = &result.error_line {
+                        valid = false;
+                        issues.push(Issue {
+                            code: "EXECUTION_ERROR".to_string(),
+                            message: error_msg.clone(),
+                            severity: "error".to_string(),
+                            line: traceback_line(&result.stderr).unwrap_or(0) as i32,
+                            column: 0,
+                            suggestions: vec![],
+                        });
+                    } else {
+                        valid = false;
+                        issues.push(Issue {
+                            code: "EXECUTION_FAIL".to_string(),
+                            message: format!("Execution failed or no output. Stderr: {}", result.stderr),
+                            severity: "error".to_string(),
+                            line: 0,
+                            column: 0,
+                            suggestions: vec![],
+                        });
+                    }
+                    execution_result = Some(result);
+                }
+                Err(e) => {
+                    valid = false;
+                    issues.push(Issue {
+                        code: "EXECUTION_SPAWN_FAIL".to_string(),
+                        message: format!("Failed to spawn python interpreter: {}", e),
+                        severity: "error".to_string(),
+                        line: 0,
+                        column: 0,
+                        suggestions: vec![],
+                    });
+                }
+            }
+        }
+
+        // 4. Golden-output Check
+        if wants_output_match {
+            if let Some(result) = &execution_result {
+                let actual = result.program_stdout();
+                if !output_match::lines_match(&req.expected_output, &actual) {
+                    valid = false;
+                    issues.push(Issue {
+                        code: "OUTPUT_MISMATCH".to_string(),
+                        message: format!(
+                            "Program output did not match the expected output:\n{}",
+                            output_match::unified_diff(&req.expected_output, &actual)
+                        ),
+                        severity: "error".to_string(),
+                        line: 0,
+                        column: 0,
+                        suggestions: vec![],
+                    });
+                }
+            }
+        }
+
+        // 5. Mechanical Lint Check (bare `except:`, and friends)
+        if req.checks.contains(&"lint".to_string()) && (req.language.to_lowercase() == "python" || req.language.is_empty()) {
+            if let Ok(Mod::Module(m)) = parse(&code, Mode::Module, "<embedded>") {
+                for bare in fixes::find_bare_excepts(&m.body, &source_map) {
+                    issues.push(Issue {
+                        code: "BARE_EXCEPT".to_string(),
+                        message: "bare `except:` catches everything, including KeyboardInterrupt/SystemExit; narrow it".to_string(),
+                        severity: "warning".to_string(),
+                        line: bare.line as i32,
+                        column: bare.column as i32,
+                        suggestions: vec![bare.suggestion],
+                    });
+                }
+            }
         }
 
+        let fixed_code = if req.apply_fixes {
+            let suggestions: Vec<_> = issues.iter().flat_map(|i| i.suggestions.clone()).collect();
+            fixes::apply_suggestions(&code, &suggestions)
+        } else {
+            String::new()
+        };
+
         Ok(Response::new(VerifyResponse {
             valid,
             score: if valid { 1.0 } else { 0.0 },
             issues,
+            fixed_code,
+        }))
+    }
+
+    type RunTestsStream = Pin<Box<dyn Stream<Item = Result<TestResult, Status>> + Send + 'static>>;
+
+    async fn run_tests(
+        &self,
+        request: Request<TestRunRequest>,
+    ) -> Result<Response<Self::RunTestsStream>, Status> {
+        let req = request.into_inner();
+        let (tx, rx) = mpsc::channel(16);
+
+        let module_ast = parse(&req.code, Mode::Module, "<embedded>")
+            .map_err(|e| Status::invalid_argument(format!("syntax error: {}", e.error)))?;
+        let Mod::Module(m) = module_ast else {
+            return Err(Status::invalid_argument("expected a module"));
+        };
+
+        let tests = test_runner::order_tests(test_runner::discover_tests(&m.body), req.shuffle_seed);
+        let limits = SandboxLimits::from_proto(req.sandbox_limits.as_ref());
+        let code = req.code;
+
+        tokio::task::spawn_blocking(move || {
+            for test in &tests {
+                let outcome = match test_runner::run_one(&code, test, limits) {
+                    Ok(outcome) => Ok(TestResult {
+                        name: outcome.name,
+                        passed: outcome.passed,
+                        duration_ms: outcome.duration_ms,
+                        failure_message: outcome.failure_message,
+                    }),
+                    Err(e) => Err(Status::internal(format!("failed to run test '{}': {}", test.name, e))),
+                };
+                if tx.blocking_send(outcome).is_err() {
+                    break; // receiver dropped; client went away
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn verify_smt(
+        &self,
+        request: Request<SmtVerifyRequest>,
+    ) -> Result<Response<SmtVerifyResponse>, Status> {
+        let req = request.into_inner();
+        let verifier = SmtVerifier::new();
+
+        let verdict = verifier
+            .verify_constraints(&req.declarations, &req.assertions, req.solver_timeout_ms)
+            .map_err(|e| Status::internal(format!("solver failure: {}", e)))?;
+
+        let response = match verdict {
+            SmtVerdict::Sat { model } => SmtVerifyResponse {
+                verdict: ProtoSmtVerdict::Sat as i32,
+                model,
+                unsat_core: vec![],
+            },
+            SmtVerdict::Unsat { unsat_core } => SmtVerifyResponse {
+                verdict: ProtoSmtVerdict::Unsat as i32,
+                model: String::new(),
+                unsat_core,
+            },
+            SmtVerdict::Unknown => SmtVerifyResponse {
+                verdict: ProtoSmtVerdict::Unknown as i32,
+                model: String::new(),
+                unsat_core: vec![],
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn verify_wasm(
+        &self,
+        request: Request<WasmVerifyRequest>,
+    ) -> Result<Response<WasmVerifyResponse>, Status> {
+        let req = request.into_inner();
+
+        let result = tokio::task::spawn_blocking(move || -> anyhow::Result<sandbox::ExecutionResult> {
+            let sandbox = WasmSandbox::new()?;
+            sandbox.execute(&req.module, req.timeout_ms, req.fuel_budget)
+        })
+        .await
+        .map_err(|e| Status::internal(format!("wasm sandbox task panicked: {}", e)))?
+        .map_err(|e| Status::internal(format!("wasm sandbox failure: {}", e)))?;
+
+        Ok(Response::new(WasmVerifyResponse {
+            output: result.output,
+            duration_ms: result.duration_ms as u64,
+            fuel_consumed: result.fuel_consumed,
+            trap: result.trap,
         }))
     }
 }
@@ -220,3 +403,46 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traceback_line_reads_the_embedded_frame() {
+        let stderr = "Traceback (most recent call last):\n  File \"<embedded>\", line 2, in <module>\nValueError: boom\n";
+        assert_eq!(traceback_line(stderr), Some(2));
+    }
+
+    #[tokio::test]
+    async fn an_uncaught_exception_reports_a_nonzero_line_number() {
+        let verifier = MyVerifier::default();
+        let request = Request::new(VerifyRequest {
+            code: "print('hi')\nraise ValueError('boom')\n".to_string(),
+            language: "python".to_string(),
+            checks: vec!["execution".to_string()],
+            sandbox_limits: None,
+            expected_output: String::new(),
+            apply_fixes: false,
+        });
+
+        let response = verifier.verify(request).await.unwrap().into_inner();
+        let issue = response
+            .issues
+            .iter()
+            .find(|i| i.code == "EXECUTION_ERROR")
+            .expect("expected an EXECUTION_ERROR issue");
+        assert_ne!(issue.line, 0, "traceback_line should resolve a real line from stderr, not fall back to 0");
+    }
+
+    #[tokio::test]
+    async fn verify_wasm_executes_a_module_through_the_rpc() {
+        let module = wat::parse_str(r#"(module (func (export "_start") i32.const 0 drop))"#).unwrap();
+        let verifier = MyVerifier::default();
+        let request = Request::new(WasmVerifyRequest { module, timeout_ms: 2_000, fuel_budget: 1_000_000 });
+
+        let response = verifier.verify_wasm(request).await.unwrap().into_inner();
+        assert!(response.trap.is_none());
+        assert!(response.fuel_consumed > 0);
+    }
+}
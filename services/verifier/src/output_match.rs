@@ -0,0 +1,158 @@
+//! Golden-output comparison for the `output_match` check.
+//!
+//! Matching is not literal: the expected text may contain `[..]` to match
+//! any run of characters on a line, and `[ROOT]`/`[TMP]` placeholders that
+//! are treated the same way (a named wildcard, normalized away rather than
+//! compared verbatim) -- mirroring the `lines_match` helper Cargo's own test
+//! suite uses to compare against golden CLI output. Trailing whitespace and
+//! CRLF/LF differences are normalized before comparing.
+
+const WILDCARDS: [&str; 3] = ["[..]", "[ROOT]", "[TMP]"];
+
+/// Does `actual` match the (possibly wildcarded) `expected` golden text?
+pub fn lines_match(expected: &str, actual: &str) -> bool {
+    let expected_lines: Vec<&str> = normalize(expected).lines().collect();
+    let actual_lines: Vec<&str> = normalize(actual).lines().collect();
+
+    if expected_lines.len() != actual_lines.len() {
+        return false;
+    }
+    expected_lines
+        .iter()
+        .zip(actual_lines.iter())
+        .all(|(e, a)| line_matches(e, a))
+}
+
+fn normalize(s: &str) -> String {
+    s.replace("\r\n", "\n")
+        .lines()
+        .map(|l| l.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn line_matches(expected: &str, actual: &str) -> bool {
+    if expected == actual {
+        return true;
+    }
+    let Some(first_wildcard) = WILDCARDS.iter().filter_map(|w| expected.find(w)).min() else {
+        return false;
+    };
+    let _ = first_wildcard;
+
+    // Split `expected` on every wildcard token into literal segments, then
+    // require `actual` to contain each segment in order. A leading/trailing
+    // segment anchors to the start/end of the line unless it is itself
+    // empty (i.e. the line starts or ends with a wildcard).
+    let segments = split_on_wildcards(expected);
+    let mut rest = actual;
+    for (idx, segment) in segments.iter().enumerate() {
+        if segment.is_empty() {
+            continue;
+        }
+        if idx == 0 {
+            if !rest.starts_with(segment.as_str()) {
+                return false;
+            }
+            rest = &rest[segment.len()..];
+        } else if idx == segments.len() - 1 {
+            if !rest.ends_with(segment.as_str()) {
+                return false;
+            }
+        } else {
+            match rest.find(segment.as_str()) {
+                Some(pos) => rest = &rest[pos + segment.len()..],
+                None => return false,
+            }
+        }
+    }
+    true
+}
+
+fn split_on_wildcards(expected: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut remaining = expected;
+    let mut current = String::new();
+    'outer: while !remaining.is_empty() {
+        for wildcard in WILDCARDS {
+            if remaining.starts_with(wildcard) {
+                segments.push(std::mem::take(&mut current));
+                remaining = &remaining[wildcard.len()..];
+                continue 'outer;
+            }
+        }
+        let mut chars = remaining.char_indices();
+        let (_, c) = chars.next().expect("remaining is non-empty");
+        current.push(c);
+        let next_idx = chars.next().map(|(i, _)| i).unwrap_or(remaining.len());
+        remaining = &remaining[next_idx..];
+    }
+    segments.push(current);
+    segments
+}
+
+/// Render a unified-style diff (`-`/`+` prefixed lines) between `expected`
+/// and `actual` so a mismatch is immediately legible.
+pub fn unified_diff(expected: &str, actual: &str) -> String {
+    let expected_lines: Vec<&str> = normalize(expected).lines().collect();
+    let actual_lines: Vec<&str> = normalize(actual).lines().collect();
+
+    let mut out = String::new();
+    let max_len = expected_lines.len().max(actual_lines.len());
+    for i in 0..max_len {
+        let e = expected_lines.get(i).copied();
+        let a = actual_lines.get(i).copied();
+        match (e, a) {
+            (Some(e), Some(a)) if line_matches(e, a) => {
+                out.push_str(&format!("  {}\n", e));
+            }
+            (Some(e), Some(a)) => {
+                out.push_str(&format!("- {}\n", e));
+                out.push_str(&format!("+ {}\n", a));
+            }
+            (Some(e), None) => out.push_str(&format!("- {}\n", e)),
+            (None, Some(a)) => out.push_str(&format!("+ {}\n", a)),
+            (None, None) => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_match() {
+        assert!(lines_match("hello\nworld\n", "hello\nworld\n"));
+    }
+
+    #[test]
+    fn wildcard_anywhere_on_line() {
+        assert!(lines_match("result: [..]\n", "result: 42\n"));
+        assert!(lines_match("[..] ms elapsed\n", "123 ms elapsed\n"));
+    }
+
+    #[test]
+    fn named_placeholder_is_a_wildcard() {
+        assert!(lines_match("loaded from [ROOT]/config.toml\n", "loaded from /tmp/x/config.toml\n"));
+    }
+
+    #[test]
+    fn trailing_whitespace_and_crlf_are_ignored() {
+        assert!(lines_match("hello  \n", "hello\r\n"));
+    }
+
+    #[test]
+    fn mismatch_is_detected() {
+        assert!(!lines_match("expected\n", "actual\n"));
+    }
+
+    #[test]
+    fn diff_marks_only_changed_lines() {
+        let diff = unified_diff("a\nb\nc\n", "a\nX\nc\n");
+        assert!(diff.contains("- b"));
+        assert!(diff.contains("+ X"));
+        assert!(diff.contains("  a"));
+    }
+}
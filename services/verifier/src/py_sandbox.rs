@@ -0,0 +1,442 @@
+//! Resource-limited execution of untrusted Python via a subprocess.
+//!
+//! Unlike a bare `Command::spawn`, every child here is bounded by OS rlimits
+//! (CPU time, address space, output size, process count) and a wall-clock
+//! deadline enforced by the host polling the child. The program text is
+//! never interpolated into a shell string; it is base64-encoded and decoded
+//! by the wrapper so triple quotes, backslashes, and other `exec()`-hostile
+//! constructs can't break out of it.
+
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+use crate::verifier::SandboxLimits as ProtoSandboxLimits;
+
+/// Resource ceilings applied to a sandboxed Python run. Mirrors the
+/// `SandboxLimits` proto message; `from_proto` fills in zeroed (omitted)
+/// fields with these defaults.
+#[derive(Debug, Clone, Copy)]
+pub struct SandboxLimits {
+    pub cpu_seconds: u64,
+    pub memory_bytes: u64,
+    pub max_output_bytes: u64,
+    pub max_processes: u64,
+    pub timeout_ms: u64,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            cpu_seconds: 5,
+            memory_bytes: 256 * 1024 * 1024,
+            max_output_bytes: 10 * 1024 * 1024,
+            max_processes: 32,
+            timeout_ms: 10_000,
+        }
+    }
+}
+
+impl SandboxLimits {
+    pub fn from_proto(limits: Option<&ProtoSandboxLimits>) -> Self {
+        let defaults = Self::default();
+        match limits {
+            None => defaults,
+            Some(l) => Self {
+                cpu_seconds: non_zero_or(l.cpu_seconds, defaults.cpu_seconds),
+                memory_bytes: non_zero_or(l.memory_bytes, defaults.memory_bytes),
+                max_output_bytes: non_zero_or(l.max_output_bytes, defaults.max_output_bytes),
+                max_processes: non_zero_or(l.max_processes, defaults.max_processes),
+                timeout_ms: non_zero_or(l.timeout_ms, defaults.timeout_ms),
+            },
+        }
+    }
+}
+
+fn non_zero_or(value: u64, default: u64) -> u64 {
+    if value == 0 { default } else { value }
+}
+
+/// Which ceiling, if any, cut the run short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitTripped {
+    Cpu,
+    Memory,
+    Timeout,
+}
+
+impl LimitTripped {
+    pub fn issue_code(self) -> &'static str {
+        match self {
+            LimitTripped::Cpu => "CPU_LIMIT",
+            LimitTripped::Memory => "MEM_LIMIT",
+            LimitTripped::Timeout => "TIMEOUT",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExecutionResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub exec_success: bool,
+    pub error_line: Option<String>,
+    pub tripped: Option<LimitTripped>,
+    pub cpu_time_ms: u64,
+    pub peak_rss_kb: u64,
+}
+
+impl ExecutionResult {
+    /// The user program's own stdout, with the wrapper's success/error
+    /// marker lines stripped out. The wrapper never writes a traceback into
+    /// this stream (it goes to real stderr instead), so this is exactly
+    /// what the program itself printed.
+    pub fn program_stdout(&self) -> String {
+        self.stdout
+            .lines()
+            .filter(|l| *l != "__EXECUTION_SUCCESS__" && !l.starts_with("__EXECUTION_ERROR__"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Run `code` under `python3` (or `python` if that fails to spawn), enforcing `limits`.
+pub fn run_python(code: &str, limits: SandboxLimits) -> Result<ExecutionResult> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(code.as_bytes());
+
+    let wrapper = format!(
+        r#"
+import base64, sys, traceback
+from io import StringIO
+
+src = base64.b64decode("{encoded}").decode("utf-8")
+old_stdout = sys.stdout
+sys.stdout = StringIO()
+exc_text = None
+try:
+    exec_globals = {{"__builtins__": __builtins__}}
+    exec(compile(src, "<embedded>", "exec"), exec_globals)
+    print("__EXECUTION_SUCCESS__")
+except Exception as e:
+    print(f"__EXECUTION_ERROR__: {{type(e).__name__}}: {{str(e)}}")
+    exc_text = traceback.format_exc()
+output = sys.stdout.getvalue()
+sys.stdout = old_stdout
+print(output, end="")
+# Print the traceback only after stdout is back to the real stream, so it
+# never ends up baked into the captured program output that output_match
+# diffs against.
+if exc_text is not None:
+    print(exc_text, file=sys.stderr)
+"#
+    );
+
+    let raw = run_wrapper_script(&wrapper, limits)?;
+    let error_line = raw
+        .stdout
+        .lines()
+        .find(|l| l.contains("__EXECUTION_ERROR__"))
+        .map(|l| l.to_string());
+
+    Ok(ExecutionResult {
+        exec_success: raw.tripped.is_none() && raw.stdout.contains("__EXECUTION_SUCCESS__"),
+        error_line,
+        stdout: raw.stdout,
+        stderr: raw.stderr,
+        tripped: raw.tripped,
+        cpu_time_ms: raw.cpu_time_ms,
+        peak_rss_kb: raw.peak_rss_kb,
+    })
+}
+
+/// Output of a sandboxed run before any marker-specific interpretation.
+/// `cpu_time_ms`/`peak_rss_kb` come from the parent's `wait4`/`getrusage`
+/// call on the reaped child, not anything the child printed -- that stays
+/// accurate even when the child was killed by a signal (RLIMIT_CPU, a
+/// timeout SIGKILL) before it could print anything itself.
+pub struct RawRun {
+    pub stdout: String,
+    pub stderr: String,
+    pub tripped: Option<LimitTripped>,
+    pub cpu_time_ms: u64,
+    pub peak_rss_kb: u64,
+}
+
+/// Write `wrapper_src` to a temp file and run it under a sandboxed Python
+/// interpreter, enforcing `limits`. Shared by every caller that builds its
+/// own wrapper script (the plain execution check, the test runner, ...).
+pub fn run_wrapper_script(wrapper_src: &str, limits: SandboxLimits) -> Result<RawRun> {
+    let mut script_path = std::env::temp_dir();
+    script_path.push(format!("axiom_exec_{}.py", uuid::Uuid::new_v4()));
+    {
+        let mut file = std::fs::File::create(&script_path).context("creating sandbox wrapper file")?;
+        file.write_all(wrapper_src.as_bytes())?;
+    }
+
+    let result = run_script_file(&script_path, limits);
+    let _ = std::fs::remove_file(&script_path);
+    result
+}
+
+fn run_script_file(script_path: &std::path::Path, limits: SandboxLimits) -> Result<RawRun> {
+    let mut child = spawn_sandboxed(script_path, limits)
+        .or_else(|_| spawn_with("python", script_path, limits))
+        .context("spawning python interpreter")?;
+
+    let mut stdout_pipe = child.stdout.take().expect("child stdout is piped");
+    let mut stderr_pipe = child.stderr.take().expect("child stderr is piped");
+    let stdout_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stdout_pipe.read_to_end(&mut buf);
+        buf
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr_pipe.read_to_end(&mut buf);
+        buf
+    });
+
+    let (tripped, cpu_time_ms, peak_rss_kb) =
+        wait_with_deadline(&mut child, Duration::from_millis(limits.timeout_ms))?;
+
+    let stdout = String::from_utf8_lossy(&stdout_reader.join().unwrap_or_default()).to_string();
+    let stderr = String::from_utf8_lossy(&stderr_reader.join().unwrap_or_default()).to_string();
+
+    Ok(RawRun { stdout, stderr, tripped, cpu_time_ms, peak_rss_kb })
+}
+
+fn spawn_sandboxed(script_path: &std::path::Path, limits: SandboxLimits) -> std::io::Result<Child> {
+    spawn_with("python3", script_path, limits)
+}
+
+fn spawn_with(interpreter: &str, script_path: &std::path::Path, limits: SandboxLimits) -> std::io::Result<Child> {
+    let mut cmd = Command::new(interpreter);
+    cmd.arg(script_path).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    #[cfg(unix)]
+    unsafe {
+        cmd.pre_exec(move || apply_rlimits(limits));
+    }
+
+    cmd.spawn()
+}
+
+#[cfg(unix)]
+fn apply_rlimits(limits: SandboxLimits) -> std::io::Result<()> {
+    // The hard CPU limit sits one second above the soft one: at the soft
+    // limit the kernel sends SIGXCPU (reported as CPU_LIMIT), whereas with
+    // soft == hard it would send SIGKILL straight away.
+    let cpu = libc::rlimit { rlim_cur: limits.cpu_seconds, rlim_max: limits.cpu_seconds.saturating_add(1) };
+    if unsafe { libc::setrlimit(libc::RLIMIT_CPU, &cpu) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    set_rlimit(libc::RLIMIT_AS, limits.memory_bytes)?;
+    set_rlimit(libc::RLIMIT_FSIZE, limits.max_output_bytes)?;
+    set_rlimit(libc::RLIMIT_NPROC, limits.max_processes)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_rlimit(resource: libc::__rlimit_resource_t, value: u64) -> std::io::Result<()> {
+    let lim = libc::rlimit { rlim_cur: value, rlim_max: value };
+    if unsafe { libc::setrlimit(resource, &lim) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Poll `child` until it exits or `timeout` elapses, SIGKILL-ing it on
+/// expiry, and return `(tripped_limit, cpu_time_ms, peak_rss_kb)`.
+///
+/// The rusage figures come from the parent's own `wait4`/`getrusage`, not
+/// anything the child printed: a child killed for exceeding `RLIMIT_CPU` or
+/// `RLIMIT_AS` never gets to print its own accounting, but the kernel still
+/// hands the reaping parent correct numbers. `std::process::Child` doesn't
+/// expose rusage at all (`try_wait`/`wait` are thin wrappers over `waitpid`
+/// with no rusage out-param), so this bypasses it and calls `libc::wait4`
+/// directly on the child's raw pid. On non-Unix there's no rusage API to
+/// call, so the two figures are always zero there.
+#[cfg(unix)]
+fn wait_with_deadline(child: &mut Child, timeout: Duration) -> Result<(Option<LimitTripped>, u64, u64)> {
+    let pid = child.id() as libc::pid_t;
+    let start = Instant::now();
+    loop {
+        if let Some((status, rusage)) = try_wait4(pid)? {
+            return Ok((classify_exit(&status), cpu_time_ms(&rusage), peak_rss_kb(&rusage)));
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let rusage = blocking_wait4(pid)?;
+            return Ok((Some(LimitTripped::Timeout), cpu_time_ms(&rusage), peak_rss_kb(&rusage)));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(not(unix))]
+fn wait_with_deadline(child: &mut Child, timeout: Duration) -> Result<(Option<LimitTripped>, u64, u64)> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((classify_exit(&status), 0, 0));
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok((Some(LimitTripped::Timeout), 0, 0));
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Non-blocking `wait4(pid, WNOHANG)`. Returns `Ok(None)` while the child is
+/// still running.
+#[cfg(unix)]
+fn try_wait4(pid: libc::pid_t) -> Result<Option<(std::process::ExitStatus, libc::rusage)>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut status: libc::c_int = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::wait4(pid, &mut status, libc::WNOHANG, &mut rusage) };
+    if ret == 0 {
+        return Ok(None);
+    }
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error()).context("wait4 failed");
+    }
+    Ok(Some((std::process::ExitStatus::from_raw(status), rusage)))
+}
+
+/// Blocking `wait4`, used once the child has already been SIGKILL-ed and we
+/// just need to reap it and read its final rusage.
+#[cfg(unix)]
+fn blocking_wait4(pid: libc::pid_t) -> Result<libc::rusage> {
+    let mut status: libc::c_int = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error()).context("wait4 failed");
+    }
+    Ok(rusage)
+}
+
+#[cfg(unix)]
+fn cpu_time_ms(rusage: &libc::rusage) -> u64 {
+    let utime_ms = rusage.ru_utime.tv_sec as u64 * 1000 + rusage.ru_utime.tv_usec as u64 / 1000;
+    let stime_ms = rusage.ru_stime.tv_sec as u64 * 1000 + rusage.ru_stime.tv_usec as u64 / 1000;
+    utime_ms + stime_ms
+}
+
+/// `ru_maxrss` is already in KB on Linux (it's in bytes on macOS, but this
+/// sandbox only targets Linux containers).
+#[cfg(unix)]
+fn peak_rss_kb(rusage: &libc::rusage) -> u64 {
+    rusage.ru_maxrss as u64
+}
+
+#[cfg(unix)]
+fn classify_exit(status: &std::process::ExitStatus) -> Option<LimitTripped> {
+    use std::os::unix::process::ExitStatusExt;
+    match status.signal() {
+        Some(libc::SIGXCPU) => Some(LimitTripped::Cpu),
+        // SIGKILL/SIGSEGV is a best-effort signal for "ran out of memory",
+        // but it's not reliable: breaching RLIMIT_AS usually just makes the
+        // next allocation fail with ENOMEM, which CPython turns into an
+        // ordinary `MemoryError` caught by the wrapper's own `except
+        // Exception` -- that surfaces as an EXECUTION_ERROR, not a signal,
+        // so MEM_LIMIT is only reported for the minority of cases where the
+        // allocator or kernel kills the process outright instead of letting
+        // the allocation fail cleanly.
+        Some(libc::SIGKILL) | Some(libc::SIGSEGV) => Some(LimitTripped::Memory),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn classify_exit(_status: &std::process::ExitStatus) -> Option<LimitTripped> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_program_runs_to_completion_and_reports_some_cpu_time() {
+        let result = run_python("print('hello')", SandboxLimits::default()).unwrap();
+        assert!(result.exec_success);
+        assert_eq!(result.program_stdout(), "hello");
+        assert!(result.tripped.is_none());
+        assert!(result.cpu_time_ms > 0, "expected the interpreter's own startup to show up as cpu time");
+    }
+
+    #[test]
+    fn an_uncaught_exception_is_reported_without_tripping_a_limit() {
+        let result = run_python("raise ValueError('boom')", SandboxLimits::default()).unwrap();
+        assert!(!result.exec_success);
+        assert!(result.tripped.is_none());
+        let error_line = result.error_line.expect("expected an error line");
+        assert!(error_line.contains("ValueError"));
+    }
+
+    #[test]
+    fn program_stdout_stays_clean_of_the_traceback_on_an_uncaught_exception() {
+        let code = "print('partial output')\nraise ValueError('boom')\n";
+        let result = run_python(code, SandboxLimits::default()).unwrap();
+        assert_eq!(result.program_stdout(), "partial output");
+        assert!(result.stderr.contains("Traceback"), "stderr was: {}", result.stderr);
+    }
+
+    #[test]
+    fn a_cpu_bound_busy_loop_trips_the_cpu_limit() {
+        let limits = SandboxLimits {
+            cpu_seconds: 1,
+            timeout_ms: 10_000,
+            ..SandboxLimits::default()
+        };
+        let result = run_python("while True:\n    pass\n", limits).unwrap();
+        assert_eq!(result.tripped, Some(LimitTripped::Cpu));
+        assert!(result.cpu_time_ms > 0, "expected nonzero cpu time even though the child was killed");
+    }
+
+    #[test]
+    fn a_sleeping_program_trips_the_wall_clock_timeout() {
+        let limits = SandboxLimits {
+            cpu_seconds: 60,
+            timeout_ms: 500,
+            ..SandboxLimits::default()
+        };
+        let result = run_python("import time\ntime.sleep(30)\n", limits).unwrap();
+        assert_eq!(result.tripped, Some(LimitTripped::Timeout));
+    }
+
+    #[test]
+    fn breaching_the_memory_ceiling_usually_surfaces_as_an_execution_error_not_a_signal() {
+        // This is the behavior documented on `classify_exit`: RLIMIT_AS makes
+        // the next allocation fail with ENOMEM, which CPython turns into an
+        // ordinary `MemoryError` the wrapper's `except Exception` catches --
+        // so MEM_LIMIT is *not* what callers should expect to see here.
+        let limits = SandboxLimits {
+            memory_bytes: 32 * 1024 * 1024,
+            ..SandboxLimits::default()
+        };
+        let code = "xs = []\nwhile True:\n    xs.append(bytearray(1024 * 1024))\n";
+        let result = run_python(code, limits).unwrap();
+        assert_ne!(result.tripped, Some(LimitTripped::Memory));
+    }
+
+    #[test]
+    fn source_with_triple_quotes_and_backslashes_survives_the_base64_round_trip() {
+        let code = "s = \"\"\"a\\nb\"\"\"\nprint(len(s))\n";
+        let result = run_python(code, SandboxLimits::default()).unwrap();
+        assert!(result.exec_success, "stdout was: {}", result.stdout);
+        assert_eq!(result.program_stdout(), "3");
+    }
+}
@@ -1,38 +1,112 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use wasi_common::pipe::WritePipe;
+use wasmtime::{Config, Engine, Linker, Module, Store, Trap};
+use wasmtime_wasi::sync::WasiCtxBuilder;
 
-/// A simulated safe sandbox for executing untrusted Code code.
-/// (WASM runtime temporarily replaced with simulation due to build environment limits)
-pub struct WasmSandbox {}
+/// A fuel-metered, epoch-interruptible sandbox for executing untrusted
+/// compiled (WASM) code. Every run is bounded by a fuel budget (deterministic
+/// "how much computation" ceiling) and a wall-clock deadline enforced via
+/// `Engine::increment_epoch` from a watchdog thread.
+pub struct WasmSandbox {
+    engine: Engine,
+}
 
 #[derive(Debug, serde::Serialize)]
 pub struct ExecutionResult {
     pub output: String,
     pub duration_ms: u128,
     pub fuel_consumed: u64,
+    /// Set when the run trapped: "FUEL_EXHAUSTED", "TIMEOUT", or
+    /// "WASM_TRAP: <message>" for anything else.
+    pub trap: Option<String>,
 }
 
 impl WasmSandbox {
     pub fn new() -> Result<Self> {
-        Ok(Self {})
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).context("building wasmtime engine")?;
+        Ok(Self { engine })
+    }
+
+    /// Instantiate `module_bytes` as a WASI command module and run its
+    /// `_start` export, metering `fuel_budget` and cutting the run off after
+    /// `timeout_ms` regardless of remaining fuel.
+    pub fn execute(&self, module_bytes: &[u8], timeout_ms: u64, fuel_budget: u64) -> Result<ExecutionResult> {
+        let module = Module::new(&self.engine, module_bytes).context("compiling wasm module")?;
+
+        let stdout_pipe = WritePipe::new_in_memory();
+        let wasi = WasiCtxBuilder::new().stdout(Box::new(stdout_pipe.clone())).build();
+
+        let mut store = Store::new(&self.engine, wasi);
+        store.set_fuel(fuel_budget).context("setting fuel budget")?;
+        store.epoch_deadline_trap();
+        store.set_epoch_deadline(1);
+
+        let mut linker: Linker<wasmtime_wasi::WasiCtx> = Linker::new(&self.engine);
+        wasmtime_wasi::add_to_linker(&mut linker, |ctx| ctx).context("linking WASI imports")?;
+
+        let watchdog_stop = self.spawn_epoch_watchdog(Duration::from_millis(timeout_ms));
+
+        let start = Instant::now();
+        let run_result: Result<()> = (|| {
+            let instance = linker.instantiate(&mut store, &module)?;
+            let start_fn = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+            start_fn.call(&mut store, ())?;
+            Ok(())
+        })();
+        let duration_ms = start.elapsed().as_millis();
+
+        watchdog_stop.store(true, Ordering::Relaxed);
+
+        let remaining_fuel = store.get_fuel().unwrap_or(0);
+        let fuel_consumed = fuel_budget.saturating_sub(remaining_fuel);
+
+        drop(store);
+        let output_bytes = stdout_pipe
+            .try_into_inner()
+            .map(|c| c.into_inner())
+            .unwrap_or_default();
+        let output = String::from_utf8_lossy(&output_bytes).to_string();
+
+        let trap = run_result.err().map(|e| classify_trap(&e));
+
+        Ok(ExecutionResult { output, duration_ms, fuel_consumed, trap })
+    }
+
+    /// Spawn a thread that bumps the engine's epoch once `timeout` elapses,
+    /// which trips the store's epoch deadline trap on its next yield point.
+    /// Returns the flag to set once the run has finished, so the watchdog
+    /// can stop polling instead of outliving the call.
+    fn spawn_epoch_watchdog(&self, timeout: Duration) -> Arc<AtomicBool> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+        let engine = self.engine.clone();
+        thread::spawn(move || {
+            let start = Instant::now();
+            while !stop_clone.load(Ordering::Relaxed) {
+                if start.elapsed() >= timeout {
+                    engine.increment_epoch();
+                    break;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+        stop
     }
+}
 
-    /// Execute binary code with strict limits (Simulated)
-    pub fn execute(&self, _code: &[u8], timeout_ms: u64) -> Result<ExecutionResult> {
-        // Simulate execution time
-        let start = std::time::Instant::now();
-        thread::sleep(Duration::from_millis(10)); // Simulate fast run
-
-        let duration = start.elapsed();
-
-        // In a real WASM runtime, we'd capture stdout.
-        // Here we just return a success signature.
-        Ok(ExecutionResult {
-            output: "Execution successful (Simulated WASM Sandbox)".to_string(),
-            duration_ms: duration.as_millis(),
-            fuel_consumed: 500,
-        })
+fn classify_trap(e: &anyhow::Error) -> String {
+    match e.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => "FUEL_EXHAUSTED".to_string(),
+        Some(Trap::Interrupt) => "TIMEOUT".to_string(),
+        Some(other) => format!("WASM_TRAP: {}", other),
+        None => format!("WASM_TRAP: {}", e),
     }
 }
 
@@ -41,10 +115,23 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_sandbox_execution() {
+    fn executes_trivial_module_and_meters_fuel() {
+        // `i32.const` is a metered operator; an empty body would consume no fuel.
+        let wasm = wat::parse_str(r#"(module (func (export "_start") i32.const 0 drop))"#).unwrap();
+        let sandbox = WasmSandbox::new().unwrap();
+        let result = sandbox.execute(&wasm, 2_000, 1_000_000).unwrap();
+        assert!(result.trap.is_none());
+        assert!(result.fuel_consumed > 0);
+    }
+
+    #[test]
+    fn out_of_fuel_is_reported() {
+        let wasm = wat::parse_str(
+            r#"(module (func (export "_start") (loop br 0)))"#,
+        )
+        .unwrap();
         let sandbox = WasmSandbox::new().unwrap();
-        let result = sandbox.execute(b"mock_code", 1000).unwrap();
-        assert!(result.output.contains("Execution successful"));
-        assert_eq!(result.fuel_consumed, 500);
+        let result = sandbox.execute(&wasm, 2_000, 1_000).unwrap();
+        assert_eq!(result.trap.as_deref(), Some("FUEL_EXHAUSTED"));
     }
 }
@@ -0,0 +1,65 @@
+//! Byte-offset -> (line, column) resolution for diagnostics.
+//!
+//! `rustpython_parser` AST nodes carry `TextRange` byte offsets rather than
+//! line/column pairs. `SourceMap` precomputes the byte offset of every line
+//! start once per request so each `Issue` tied to a source position can
+//! report a real, 1-based `(line, column)` instead of `(0, 0)`. Issues with
+//! no source position (coverage summaries, resource limits, output
+//! mismatches) still report `(0, 0)`.
+
+pub struct SourceMap {
+    source: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new(src: &str) -> Self {
+        let mut line_starts = vec![0];
+        for (i, b) in src.bytes().enumerate() {
+            if b == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+        Self { source: src.to_string(), line_starts }
+    }
+
+    /// The original source text this map was built from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Convert a 0-based byte offset into a 1-based `(line, column)` pair.
+    pub fn location(&self, offset: usize) -> (u32, u32) {
+        let line_idx = match self.line_starts.binary_search(&offset) {
+            Ok(idx) => idx,
+            Err(idx) => idx - 1,
+        };
+        let line_start = self.line_starts[line_idx];
+        let line = (line_idx + 1) as u32;
+        let column = (offset - line_start + 1) as u32;
+        (line, column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_line_first_column() {
+        let map = SourceMap::new("abc\ndef\n");
+        assert_eq!(map.location(0), (1, 1));
+    }
+
+    #[test]
+    fn start_of_second_line() {
+        let map = SourceMap::new("abc\ndef\n");
+        assert_eq!(map.location(4), (2, 1));
+    }
+
+    #[test]
+    fn middle_of_third_line() {
+        let map = SourceMap::new("abc\ndef\nghijk\n");
+        assert_eq!(map.location(10), (3, 3));
+    }
+}
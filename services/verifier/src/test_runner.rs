@@ -0,0 +1,238 @@
+//! Discovers `test_*` functions and `unittest.TestCase` methods in submitted
+//! Python, then executes each in its own sandboxed subprocess and reports
+//! individual pass/fail results -- mirroring how Deno's test runner emits
+//! per-test events rather than one terminal verdict.
+
+use anyhow::Result;
+use base64::Engine;
+use rand::rngs::SmallRng;
+use rand::{seq::SliceRandom, SeedableRng};
+use rustpython_parser::ast::{self, Stmt};
+
+use crate::py_sandbox::{self, SandboxLimits};
+
+/// A single `test_*` unit found by static discovery.
+pub struct DiscoveredTest {
+    /// Display name: `test_foo` for a bare function, `SomeCase.test_foo`
+    /// for a `unittest.TestCase` method.
+    pub name: String,
+    /// Python expression that invokes the test once `code` has been exec'd
+    /// into the namespace, e.g. `test_foo()` or `SomeCase().test_foo()`.
+    pub call_expr: String,
+}
+
+pub struct TestOutcome {
+    pub name: String,
+    pub passed: bool,
+    pub duration_ms: u64,
+    pub failure_message: String,
+}
+
+/// Walk top-level statements for `test_*` functions and `TestCase`
+/// subclasses, matching the same `Stmt::FunctionDef`/`Stmt::ClassDef` arms
+/// `DocstringChecker` does -- only top-level defs and the direct methods of
+/// a top-level `TestCase` class are found; nested definitions are not.
+pub fn discover_tests(body: &[Stmt]) -> Vec<DiscoveredTest> {
+    let mut tests = Vec::new();
+    for stmt in body {
+        match stmt {
+            Stmt::FunctionDef(f) if f.name.as_str().starts_with("test_") => {
+                tests.push(DiscoveredTest {
+                    name: f.name.to_string(),
+                    call_expr: format!("{}()", f.name.as_str()),
+                });
+            }
+            Stmt::ClassDef(c) if is_test_case(c) => {
+                for inner in &c.body {
+                    if let Stmt::FunctionDef(m) = inner {
+                        if m.name.as_str().starts_with("test_") {
+                            tests.push(DiscoveredTest {
+                                name: format!("{}.{}", c.name.as_str(), m.name.as_str()),
+                                call_expr: format!("{}().{}()", c.name.as_str(), m.name.as_str()),
+                            });
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    tests
+}
+
+fn is_test_case(class_def: &ast::StmtClassDef) -> bool {
+    class_def.bases.iter().any(|base| base_name(base).as_deref() == Some("TestCase"))
+}
+
+fn base_name(expr: &ast::Expr) -> Option<String> {
+    match expr {
+        ast::Expr::Name(n) => Some(n.id.to_string()),
+        // unittest.TestCase: only the trailing attribute matters here.
+        ast::Expr::Attribute(a) => Some(a.attr.to_string()),
+        _ => None,
+    }
+}
+
+/// Apply an optional seeded shuffle to discovery order, so a caller can
+/// reproduce (or rule out) inter-test ordering dependencies.
+pub fn order_tests(mut tests: Vec<DiscoveredTest>, shuffle_seed: Option<u64>) -> Vec<DiscoveredTest> {
+    if let Some(seed) = shuffle_seed {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        tests.shuffle(&mut rng);
+    }
+    tests
+}
+
+/// Run one discovered test in its own sandboxed subprocess and report the
+/// result. `code` is re-exec'd for every test so one test's mutations to
+/// module state can't leak into the next.
+pub fn run_one(code: &str, test: &DiscoveredTest, limits: SandboxLimits) -> Result<TestOutcome> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(code.as_bytes());
+
+    let wrapper = format!(
+        r#"
+import base64, time, traceback
+
+src = base64.b64decode("{encoded}").decode("utf-8")
+exec_globals = {{"__builtins__": __builtins__}}
+start = time.monotonic()
+try:
+    exec(compile(src, "<embedded>", "exec"), exec_globals)
+    exec(compile("{call_expr}", "<embedded>", "eval"), exec_globals)
+    duration_ms = int((time.monotonic() - start) * 1000)
+    print(f"__TEST_PASS__ duration_ms={{duration_ms}}")
+except Exception as e:
+    duration_ms = int((time.monotonic() - start) * 1000)
+    print(f"__TEST_FAIL__ duration_ms={{duration_ms}}")
+    print(f"{{type(e).__name__}}: {{e}}")
+    print(traceback.format_exc())
+"#,
+        call_expr = test.call_expr,
+    );
+
+    let raw = py_sandbox::run_wrapper_script(&wrapper, limits)?;
+
+    if let Some(tripped) = raw.tripped {
+        return Ok(TestOutcome {
+            name: test.name.clone(),
+            passed: false,
+            duration_ms: limits.timeout_ms,
+            failure_message: format!("{} ({})", tripped.issue_code(), "test exceeded its sandbox limits"),
+        });
+    }
+
+    if let Some(pass_line) = raw.stdout.lines().find(|l| l.starts_with("__TEST_PASS__")) {
+        return Ok(TestOutcome {
+            name: test.name.clone(),
+            passed: true,
+            duration_ms: duration_field(pass_line),
+            failure_message: String::new(),
+        });
+    }
+
+    let fail_line = raw.stdout.lines().find(|l| l.starts_with("__TEST_FAIL__"));
+    let duration_ms = fail_line.map(duration_field).unwrap_or(0);
+    let failure_message = raw
+        .stdout
+        .lines()
+        .skip_while(|l| !l.starts_with("__TEST_FAIL__"))
+        .skip(1)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(TestOutcome {
+        name: test.name.clone(),
+        passed: false,
+        duration_ms,
+        failure_message: if failure_message.trim().is_empty() {
+            format!("test did not run to completion. stderr: {}", raw.stderr)
+        } else {
+            failure_message
+        },
+    })
+}
+
+fn duration_field(line: &str) -> u64 {
+    line.split("duration_ms=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustpython_parser::ast::Mod;
+    use rustpython_parser::{parse, Mode};
+
+    fn module_body(code: &str) -> Vec<Stmt> {
+        match parse(code, Mode::Module, "<test>").unwrap() {
+            Mod::Module(m) => m.body,
+            _ => panic!("expected a module"),
+        }
+    }
+
+    #[test]
+    fn discovers_top_level_test_functions_but_not_helpers() {
+        let body = module_body(
+            "def test_one():\n    pass\n\ndef helper():\n    pass\n\ndef test_two():\n    pass\n",
+        );
+        let names: Vec<_> = discover_tests(&body).into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["test_one", "test_two"]);
+    }
+
+    #[test]
+    fn discovers_test_case_methods_qualified_by_class_name() {
+        let body = module_body(
+            "import unittest\n\nclass MyCase(unittest.TestCase):\n    def test_a(self):\n        pass\n    def helper(self):\n        pass\n",
+        );
+        let tests = discover_tests(&body);
+        assert_eq!(tests.len(), 1);
+        assert_eq!(tests[0].name, "MyCase.test_a");
+        assert_eq!(tests[0].call_expr, "MyCase().test_a()");
+    }
+
+    #[test]
+    fn ignores_classes_that_are_not_test_cases() {
+        let body = module_body("class Plain:\n    def test_a(self):\n        pass\n");
+        assert!(discover_tests(&body).is_empty());
+    }
+
+    #[test]
+    fn no_seed_preserves_discovery_order() {
+        let body = module_body("def test_a():\n    pass\ndef test_b():\n    pass\ndef test_c():\n    pass\n");
+        let tests = discover_tests(&body);
+        let names: Vec<_> = order_tests(tests, None).into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["test_a", "test_b", "test_c"]);
+    }
+
+    #[test]
+    fn the_same_seed_always_produces_the_same_order() {
+        let body = module_body("def test_a():\n    pass\ndef test_b():\n    pass\ndef test_c():\n    pass\n");
+        let first = order_tests(discover_tests(&body), Some(42));
+        let second = order_tests(discover_tests(&body), Some(42));
+        let first_names: Vec<_> = first.into_iter().map(|t| t.name).collect();
+        let second_names: Vec<_> = second.into_iter().map(|t| t.name).collect();
+        assert_eq!(first_names, second_names);
+    }
+
+    #[test]
+    fn run_one_reports_a_passing_test() {
+        let body = module_body("def test_ok():\n    assert 1 + 1 == 2\n");
+        let test = &discover_tests(&body)[0];
+        let outcome = run_one("def test_ok():\n    assert 1 + 1 == 2\n", test, SandboxLimits::default()).unwrap();
+        assert!(outcome.passed);
+        assert_eq!(outcome.name, "test_ok");
+    }
+
+    #[test]
+    fn run_one_reports_a_failing_test_with_its_assertion_message() {
+        let code = "def test_fails():\n    assert False, 'went wrong'\n";
+        let body = module_body(code);
+        let test = &discover_tests(&body)[0];
+        let outcome = run_one(code, test, SandboxLimits::default()).unwrap();
+        assert!(!outcome.passed);
+        assert!(outcome.failure_message.contains("went wrong"));
+    }
+}